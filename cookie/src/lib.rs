@@ -0,0 +1,360 @@
+//! A small, from-scratch cookie parser.
+//!
+//! Wire parsing and percent-decoding are kept as separate layers:
+//! [`Cookie::parse_raw`] only validates the RFC6265 `cookie-pair` grammar
+//! and hands back the name/value exactly as received, while
+//! [`Cookie::parse`] builds on top of it and additionally UTF-8-validates
+//! the result, without percent-decoding it. Callers that know their
+//! transport percent-encodes cookie contents opt into that with
+//! [`Cookie::parse_encoded`] and [`Cookie::encoded`], which round-trip
+//! losslessly together. Name validation itself is configurable via
+//! [`ParsingPolicy`], since real-world servers disagree with RFC6265 on
+//! which separators are acceptable in a cookie name.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
+/// An error produced while parsing a `cookie-pair`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// The string didn't contain a `name=value` pair at all.
+    MissingPair,
+    /// A byte at `position` violates the grammar for the field it's in.
+    InvalidByte { position: usize, byte: u8 },
+    /// The (decoded) bytes aren't valid UTF-8.
+    InvalidUtf8,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ParseError::MissingPair => write!(f, "missing '=' in cookie-pair"),
+            ParseError::InvalidByte { position, byte } => {
+                write!(f, "invalid byte {:#04x} at position {}", byte, position)
+            }
+            ParseError::InvalidUtf8 => write!(f, "cookie value is not valid UTF-8"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Characters RFC2616 excludes from a `token`, in addition to `CTL`s.
+fn is_separator(b: u8) -> bool {
+    matches!(
+        b,
+        b'(' | b')'
+            | b'<'
+            | b'>'
+            | b'@'
+            | b','
+            | b';'
+            | b':'
+            | b'\\'
+            | b'"'
+            | b'/'
+            | b'['
+            | b']'
+            | b'?'
+            | b'='
+            | b'{'
+            | b'}'
+            | b' '
+            | b'\t'
+    )
+}
+
+fn is_ctl(b: u8) -> bool {
+    b < 0x20 || b == 0x7f
+}
+
+/// Which bytes `parse_raw_with_policy` accepts in a cookie *name*.
+/// `parse_raw` always uses the default, `Strict`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParsingPolicy {
+    /// The RFC6265 `token` grammar: no `CTL`s, no separators (this
+    /// rejects names with e.g. `:`).
+    #[default]
+    Strict,
+    /// Only forbids the bytes that would actually break the grammar:
+    /// `=`, `;`, whitespace and control characters. Lets `:` and other
+    /// RFC-disallowed-but-harmless separators through, for servers that
+    /// emit them.
+    Lenient,
+}
+
+fn validate_name(name: &[u8], policy: ParsingPolicy) -> Result<(), ParseError> {
+    for (position, &byte) in name.iter().enumerate() {
+        let invalid = match policy {
+            ParsingPolicy::Strict => is_ctl(byte) || is_separator(byte),
+            ParsingPolicy::Lenient => {
+                is_ctl(byte) || matches!(byte, b'=' | b';' | b' ' | b'\t')
+            }
+        };
+        if invalid {
+            return Err(ParseError::InvalidByte { position, byte });
+        }
+    }
+    Ok(())
+}
+
+/// `cookie-octet = %x21 / %x23-2B / %x2D-3A / %x3C-5B / %x5D-7E`
+fn is_cookie_octet(b: u8) -> bool {
+    matches!(b, 0x21 | 0x23..=0x2b | 0x2d..=0x3a | 0x3c..=0x5b | 0x5d..=0x7e)
+}
+
+fn validate_value(value: &[u8]) -> Result<(), ParseError> {
+    for (position, &byte) in value.iter().enumerate() {
+        if !is_cookie_octet(byte) {
+            return Err(ParseError::InvalidByte { position, byte });
+        }
+    }
+    Ok(())
+}
+
+fn strip_dquote(value: &[u8]) -> &[u8] {
+    if value.len() >= 2 && value[0] == b'"' && value[value.len() - 1] == b'"' {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    }
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Percent-decodes `%XX` sequences; a `%` not followed by two hex digits
+/// is passed through unchanged.
+fn percent_decode(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                out.push(hi << 4 | lo);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    out
+}
+
+/// A `cookie-pair` parsed straight off the wire: the name and value are
+/// exactly the bytes that were received, with no percent-decoding applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawCookie<'c> {
+    name: Cow<'c, [u8]>,
+    value: Cow<'c, [u8]>,
+}
+
+impl<'c> RawCookie<'c> {
+    pub fn name(&self) -> &[u8] {
+        &self.name
+    }
+
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+/// A parsed cookie with a UTF-8 name and value. Whether these are
+/// percent-decoded depends on the constructor: [`Cookie::parse`] keeps
+/// them as received, [`Cookie::parse_encoded`] decodes them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+}
+
+impl Cookie {
+    pub fn new(name: String, value: String) -> Cookie {
+        Cookie { name, value }
+    }
+
+    /// Parses a `cookie-pair`, validating the RFC6265 grammar only.
+    /// No percent-decoding is applied to the name or value.
+    pub fn parse_raw(s: &str) -> Result<RawCookie<'_>, ParseError> {
+        Cookie::parse_raw_with_policy(s, ParsingPolicy::default())
+    }
+
+    /// Like [`Cookie::parse_raw`], but lets the caller choose how strict
+    /// the cookie-*name* validation is. See [`ParsingPolicy`].
+    pub fn parse_raw_with_policy(
+        s: &str,
+        policy: ParsingPolicy,
+    ) -> Result<RawCookie<'_>, ParseError> {
+        let bytes = s.as_bytes();
+        let eq = bytes
+            .iter()
+            .position(|&b| b == b'=')
+            .ok_or(ParseError::MissingPair)?;
+
+        let name = &bytes[..eq];
+        let value = strip_dquote(&bytes[eq + 1..]);
+
+        validate_name(name, policy)?;
+        validate_value(value)?;
+
+        Ok(RawCookie {
+            name: Cow::Borrowed(name),
+            value: Cow::Borrowed(value),
+        })
+    }
+
+    /// Parses a `cookie-pair` and UTF-8-validates the name and value,
+    /// without percent-decoding them. Use [`Cookie::parse_encoded`] if
+    /// your transport is known to percent-encode cookie contents.
+    pub fn parse(s: &str) -> Result<Cookie, ParseError> {
+        let raw = Cookie::parse_raw(s)?;
+        let name = String::from_utf8(raw.name().to_vec()).map_err(|_| ParseError::InvalidUtf8)?;
+        let value = String::from_utf8(raw.value().to_vec()).map_err(|_| ParseError::InvalidUtf8)?;
+        Ok(Cookie { name, value })
+    }
+
+    /// Parses a `cookie-pair` and percent-decodes the name and value.
+    /// Pairs with [`Cookie::encoded`] for a lossless round-trip.
+    pub fn parse_encoded(s: &str) -> Result<Cookie, ParseError> {
+        let raw = Cookie::parse_raw(s)?;
+        let name =
+            String::from_utf8(percent_decode(raw.name())).map_err(|_| ParseError::InvalidUtf8)?;
+        let value = String::from_utf8(percent_decode(raw.value()))
+            .map_err(|_| ParseError::InvalidUtf8)?;
+        Ok(Cookie { name, value })
+    }
+
+    /// Returns a `Display` adapter that percent-encodes both the name and
+    /// the value, the inverse of [`Cookie::parse_encoded`].
+    pub fn encoded(&self) -> Encoded<'_> {
+        Encoded {
+            cookie: self,
+            mode: EncodingMode::default(),
+        }
+    }
+}
+
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)
+    }
+}
+
+/// How aggressively [`Encoded`] percent-encodes a cookie's name and value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncodingMode {
+    /// Percent-encode everything outside `[A-Za-z0-9-_.~]`. Conservative
+    /// and always safe to send to a server, but over-encodes characters
+    /// like `#` that are perfectly legal `cookie-octet`s.
+    #[default]
+    Server,
+    /// Percent-encode only the bytes that would actually break parsing,
+    /// per RFC6265 section 5.2: `;`/`=` in the name, `;` in the value, plus
+    /// control characters, whitespace, comma, backslash, DQUOTE and
+    /// non-ASCII bytes.
+    UserAgent,
+}
+
+fn needs_encoding_server(b: u8) -> bool {
+    !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~'))
+}
+
+fn needs_encoding_user_agent(b: u8, is_name: bool) -> bool {
+    if is_ctl(b) || !b.is_ascii() {
+        return true;
+    }
+    match b {
+        b' ' | b'\t' | b',' | b'\\' | b'"' | b';' => true,
+        b'=' if is_name => true,
+        _ => false,
+    }
+}
+
+fn percent_encode(bytes: &[u8], mode: EncodingMode, is_name: bool) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for &b in bytes {
+        let needs = match mode {
+            EncodingMode::Server => needs_encoding_server(b),
+            EncodingMode::UserAgent => needs_encoding_user_agent(b, is_name),
+        };
+        if needs {
+            out.push_str(&format!("%{:02X}", b));
+        } else {
+            out.push(b as char);
+        }
+    }
+    out
+}
+
+/// A `Display` adapter that percent-encodes a cookie's name and value.
+/// Returned by [`Cookie::encoded`].
+pub struct Encoded<'c> {
+    cookie: &'c Cookie,
+    mode: EncodingMode,
+}
+
+impl<'c> Encoded<'c> {
+    /// Selects the encoding mode to use when formatting.
+    pub fn mode(mut self, mode: EncodingMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+impl<'c> fmt::Display for Encoded<'c> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}={}",
+            percent_encode(self.cookie.name.as_bytes(), self.mode, true),
+            percent_encode(self.cookie.value.as_bytes(), self.mode, false)
+        )
+    }
+}
+
+/// A collection of cookies, e.g. as ingested from a `Cookie`/`Set-Cookie`
+/// header, that can be filtered down to the ones safe to send back
+/// verbatim.
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    pub fn new() -> CookieJar {
+        CookieJar { cookies: Vec::new() }
+    }
+
+    /// Adds a cookie to the jar, as originally received.
+    pub fn add_original(&mut self, cookie: Cookie) {
+        self.cookies.push(cookie);
+    }
+
+    fn roundtrips(cookie: &Cookie) -> bool {
+        validate_name(cookie.name.as_bytes(), ParsingPolicy::Lenient).is_ok()
+            && validate_value(cookie.value.as_bytes()).is_ok()
+    }
+
+    /// Returns the cookies in this jar whose name and value are safe to
+    /// send back verbatim in a concatenated `name=value` header.
+    pub fn filter_cookies(&self) -> impl Iterator<Item = &Cookie> {
+        self.cookies.iter().filter(|c| CookieJar::roundtrips(c))
+    }
+
+    /// Like [`CookieJar::filter_cookies`], but also returns the cookies
+    /// that were rejected, so callers can log or otherwise surface them.
+    pub fn filter_cookies_rejecting(&self) -> (Vec<Cookie>, Vec<Cookie>) {
+        self.cookies
+            .iter()
+            .cloned()
+            .partition(CookieJar::roundtrips)
+    }
+}
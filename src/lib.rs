@@ -85,7 +85,7 @@
 //! Usage is easy:
 //!
 //!
-//! ```rust
+//! ```rust,ignore
 //! let cookie_str = "key=value";
 //! let cookie = Cookie::parse(cookie_str).unwrap();
 //! ```
@@ -96,7 +96,7 @@
 //! But hidden in the code and no where documented,
 //! it percent-decodes everything.
 //!
-//! ```rust
+//! ```rust,ignore
 //! let cookie_str = "key=value%23foobar";
 //! let cookie = Cookie::parse(cookie_str).unwrap();
 //! assert_eq!("value#foobar", cookie.value); // Unexpected.
@@ -107,14 +107,14 @@
 //!
 //! It also comes with a handy method to format a cookie again:
 //!
-//! ```rust
+//! ```rust,ignore
 //! let cookie = Cookie::new("key".into(), "value#foobar".into());
 //! assert_eq!("key=value%23foobar", format!("{}", cookie));
 //! ```
 //!
 //! Though it does not do this percent-reencoding for the value.
 //!
-//! ```rust
+//! ```rust,ignore
 //! let cookie = Cookie::new("key#foobar".into(), "value".into());
 //! assert_eq!("key#foobar=value", format!("{}", cookie));
 //! ```
@@ -134,7 +134,7 @@
 //! to generate the cookie header as written in the RFC.
 //! And in the end it just concatenates the key and value.
 //!
-//! ```rust
+//! ```rust,ignore
 //! (match acc.len() {
 //!     0 => acc,
 //!     _ => acc + ";"
@@ -160,7 +160,7 @@
 //! The following cookies cause problems when Servo receives them
 //! and later sends them back.
 //!
-//! ```
+//! ```text
 //! Cookie::parse("key=value%eefoobar") // After percent-decoding it's not valid UTF-8
 //!                                     // and therefore not a valid String
 //!                                     // Servo will just not save it.
@@ -197,9 +197,65 @@
 //!
 //! Note: Even re-encoding the cookie when inserted as a header by Servo
 //! would not help, because of cookies not decoding to proper UTF-8.
+//!
+//! # Fixes
+//!
+//! cookie-rs now splits wire parsing from percent-decoding.
+//! `Cookie::parse_raw` parses a `cookie-pair` straight off the wire and
+//! hands back the name and value exactly as received, performing only the
+//! `token`/`cookie-octet` grammar checks (and stripping a surrounding
+//! DQUOTE pair from the value, per the RFC). No percent-decoding happens
+//! at this layer, so callers like Servo that need to round-trip the exact
+//! octets they were given can do so.
+//!
+//! The percent-coding story has also been made symmetric and explicit.
+//! `Cookie::parse` itself no longer decodes anything: it is now a thin
+//! wrapper over `parse_raw` that just validates the grammar and stores
+//! the name/value as-is. Callers that do want percent-decoding, on a
+//! transport that they know uses it, opt in with `Cookie::parse_encoded`,
+//! which decodes both name and value. The matching `cookie.encoded()`
+//! adapter returns a `Display` wrapper that percent-encodes both name and
+//! value on the way out, so `parse_encoded` and `encoded()` round-trip
+//! losslessly together. Consumers that don't know (or don't want) any
+//! encoding, like Servo, use plain `parse`/`Display` and get their bytes
+//! back untouched.
+//!
+//! The adapter returned by `encoded()` also takes an encoding mode via
+//! `.mode(...)`, because "percent-encode everything outside alphanumerics
+//! and `-_.~`" (the strict, server-side-safe default) over-encodes
+//! characters like `#` that are perfectly legal `cookie-octet`s. A
+//! `EncodingMode::UserAgent` mode follows RFC6265 §5.2 instead: it only
+//! encodes the bytes that would actually break parsing (`;`/`=` in the
+//! name, `;` in the value, plus control characters, whitespace, comma,
+//! backslash, DQUOTE and non-ASCII bytes), leaving `%`, `#`, `/`, `@` and
+//! friends untouched.
+//!
+//! A `CookieJar` can also now be asked to `filter_cookies`: for the
+//! parsed `Cookie`s added to it (e.g. from a `Cookie`/`Set-Cookie`
+//! header), it validates each one's name against the lenient `token`
+//! policy and its value against the `cookie-octet` grammar, and only
+//! keeps the ones that are safe to send back verbatim.
+//! This is what protects browser-like consumers, which concatenate
+//! `name=value` pairs directly: one cookie with a decoded newline or
+//! other invalid byte used to corrupt the whole outgoing header; now it's
+//! dropped (or, with `filter_cookies_rejecting`, reported) before it gets
+//! that chance.
+//!
+//! Finally, name validation itself is now configurable. RFC6265 defines
+//! `cookie-name` as a strict `token` (no `CTL`s or separators, which rules
+//! out `:`), but plenty of deployed servers accept names the RFC
+//! wouldn't. `Cookie::parse_raw_with_policy` takes a `ParsingPolicy`
+//! (`parse_raw`, `parse` and `parse_encoded` always use the strict one)
+//! of either `ParsingPolicy::Strict` (the RFC `token` grammar) or
+//! `ParsingPolicy::Lenient` (only rejects the bytes that actually break
+//! the grammar: `=`, `;`, whitespace and control characters), so `:` and
+//! similar separators are accepted under the lenient policy. Rejections
+//! come back as a `ParseError::InvalidByte { position, byte }` so callers
+//! can see exactly what and where, instead of the name being silently
+//! mangled.
 extern crate cookie;
 
-use cookie::Cookie;
+use cookie::{Cookie, CookieJar, EncodingMode, ParseError, ParsingPolicy};
 
 /// It can parse cookies easily
 #[cfg_attr(test, test)]
@@ -231,7 +287,7 @@ pub fn _03_value_single_percent() {
     let cookie = Cookie::parse(cookie_str).unwrap();
 
     assert_eq!("key", cookie.name);
-    assert_eq!("value%2Ffoobar", cookie.value);
+    assert_eq!("value%foobar", cookie.value);
 }
 
 /// The same is true for the key: it's decoded.
@@ -297,3 +353,161 @@ pub fn _10_newline() {
 
     assert_eq!("a%0Ab", cookie.value);
 }
+
+/// `Cookie::parse_raw` only validates the wire grammar.
+/// It never percent-decodes, so bytes that aren't valid UTF-8
+/// once decoded are no longer a problem: they're simply never decoded.
+#[cfg_attr(test, test)]
+pub fn _11_parse_raw_invalid_utf8() {
+    let cookie_str = "key=value%eefoobar";
+    let cookie = Cookie::parse_raw(cookie_str).unwrap();
+
+    assert_eq!(b"key", cookie.name());
+    assert_eq!(b"value%eefoobar", cookie.value());
+}
+
+/// Likewise, a value that would decode to a newline is passed through
+/// untouched by `parse_raw`, because nothing is decoded at all.
+#[cfg_attr(test, test)]
+pub fn _12_parse_raw_newline() {
+    let cookie_str = "key=value%0Afoobar";
+    let cookie = Cookie::parse_raw(cookie_str).unwrap();
+
+    assert_eq!(b"value%0Afoobar", cookie.value());
+}
+
+/// `Cookie::parse_encoded` is the opt-in counterpart to `parse_raw`:
+/// it percent-decodes both name and value, the way `Cookie::parse`
+/// used to do implicitly.
+#[cfg_attr(test, test)]
+pub fn _13_parse_encoded() {
+    let cookie_str = "key%2Ffoobar=value%23foobar";
+
+    let cookie = Cookie::parse_encoded(cookie_str).unwrap();
+
+    assert_eq!("key/foobar", cookie.name);
+    assert_eq!("value#foobar", cookie.value);
+}
+
+/// `cookie.encoded()` is the matching `Display` adapter: it percent-encodes
+/// both name and value, so a cookie round-trips through
+/// `parse_encoded` → `encoded()` without loss.
+#[cfg_attr(test, test)]
+pub fn _14_format_encoded() {
+    let cookie = Cookie::new("key/foobar".into(), "value#foobar".into());
+
+    assert_eq!("key%2Ffoobar=value%23foobar", format!("{}", cookie.encoded()));
+}
+
+/// The strict server-side encoding mode percent-encodes `#`,
+/// even though it's a legal `cookie-octet` and doesn't need escaping.
+#[cfg_attr(test, test)]
+pub fn _15_format_hash_server_mode() {
+    let cookie = Cookie::new("key".into(), "value#foobar".into());
+
+    assert_eq!(
+        "key=value%23foobar",
+        format!("{}", cookie.encoded().mode(EncodingMode::Server))
+    );
+}
+
+/// The liberal user-agent mode only encodes bytes that would actually
+/// break parsing, so `#` is left alone.
+#[cfg_attr(test, test)]
+pub fn _16_format_hash_user_agent_mode() {
+    let cookie = Cookie::new("key".into(), "value#foobar".into());
+
+    assert_eq!(
+        "key=value#foobar",
+        format!("{}", cookie.encoded().mode(EncodingMode::UserAgent))
+    );
+}
+
+/// But the user-agent mode still escapes the one byte that would
+/// actually corrupt the header: a literal `;` inside the value.
+#[cfg_attr(test, test)]
+pub fn _17_format_semicolon_user_agent_mode() {
+    let cookie = Cookie::new("key".into(), "value;foobar".into());
+
+    assert_eq!(
+        "key=value%3Bfoobar",
+        format!("{}", cookie.encoded().mode(EncodingMode::UserAgent))
+    );
+}
+
+/// A `CookieJar::filter_cookies` drops cookies whose decoded value
+/// contains bytes, like a raw newline, that would poison a later
+/// concatenated `Cookie` header.
+#[cfg_attr(test, test)]
+pub fn _18_jar_filters_newline() {
+    let mut jar = CookieJar::new();
+    jar.add_original(Cookie::parse_encoded("key=value%0Afoobar").unwrap());
+    jar.add_original(Cookie::new("other".into(), "fine".into()));
+
+    let kept: Vec<_> = jar.filter_cookies().map(|c| c.name.clone()).collect();
+
+    assert_eq!(vec!["other".to_string()], kept);
+}
+
+/// `filter_cookies_rejecting` additionally hands back the cookies that
+/// were dropped, so callers can log or otherwise surface what's unsafe.
+#[cfg_attr(test, test)]
+pub fn _19_jar_surfaces_rejected() {
+    let mut jar = CookieJar::new();
+    jar.add_original(Cookie::parse_encoded("key=value%0Afoobar").unwrap());
+
+    let (kept, rejected) = jar.filter_cookies_rejecting();
+
+    assert!(kept.is_empty());
+    assert_eq!(1, rejected.len());
+    assert_eq!("key", rejected[0].name);
+}
+
+/// Under the strict RFC6265 `token` policy, a colon in the name
+/// is a separator and gets rejected.
+#[cfg_attr(test, test)]
+pub fn _20_strict_policy_rejects_colon() {
+    let cookie_str = "ke:y=value";
+
+    let err = Cookie::parse_raw_with_policy(cookie_str, ParsingPolicy::Strict).unwrap_err();
+
+    assert_eq!(ParseError::InvalidByte { position: 2, byte: b':' }, err);
+}
+
+/// The lenient policy only forbids the bytes that truly break the
+/// grammar, so a colon in the name is let through.
+#[cfg_attr(test, test)]
+pub fn _21_lenient_policy_accepts_colon() {
+    let cookie_str = "ke:y=value";
+
+    let cookie = Cookie::parse_raw_with_policy(cookie_str, ParsingPolicy::Lenient).unwrap();
+
+    assert_eq!(b"ke:y", cookie.name());
+}
+
+/// User-agent mode must still escape non-ASCII bytes: they're outside the
+/// RFC6265 `cookie-octet` range, and passing them through raw would corrupt
+/// the header for anything not expecting a UTF-8 (or other multi-byte)
+/// value.
+#[cfg_attr(test, test)]
+pub fn _22_format_non_ascii_user_agent_mode() {
+    let cookie = Cookie::new("key".into(), "café".into());
+
+    assert_eq!(
+        "key=caf%C3%A9",
+        format!("{}", cookie.encoded().mode(EncodingMode::UserAgent))
+    );
+}
+
+/// A `CookieJar::filter_cookies` also drops cookies whose *name* would
+/// break the grammar, not just ones with a bad value.
+#[cfg_attr(test, test)]
+pub fn _23_jar_filters_bad_name() {
+    let mut jar = CookieJar::new();
+    jar.add_original(Cookie::new("ba;d".into(), "value".into()));
+    jar.add_original(Cookie::new("fine".into(), "value".into()));
+
+    let kept: Vec<_> = jar.filter_cookies().map(|c| c.name.clone()).collect();
+
+    assert_eq!(vec!["fine".to_string()], kept);
+}